@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AudioFormat {
+    Mp3,
+    Wav,
+    M4a,
+    Aac,
+    Flac,
+}
+
+impl AudioFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Flac => "flac",
+        }
+    }
+}
+
+/// Persisted answers to the prompts `main` would otherwise ask every run,
+/// plus the tool paths `check_dependencies` would otherwise re-derive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub yt_dlp_path: PathBuf,
+    pub ffmpeg_path: Option<PathBuf>,
+    pub audio_format: AudioFormat,
+    pub download_dir: String,
+    pub embed_thumbnail: bool,
+    pub concurrency: usize,
+    pub genre_output_dirs: Option<HashMap<String, String>>,
+}
+
+fn config_path() -> PathBuf {
+    PathBuf::from("ytpd").join("config.json")
+}
+
+/// Loads `ytpd/config.json` if it exists and parses cleanly. Any missing or
+/// malformed file is treated as "no config yet" rather than an error, since
+/// falling back to the interactive flow is always a safe default.
+pub fn load() -> Option<Config> {
+    let contents = fs::read_to_string(config_path()).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn save(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = config_path().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(config)?;
+    fs::write(config_path(), contents)?;
+    Ok(())
+}
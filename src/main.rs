@@ -1,16 +1,23 @@
+use crate::config::AudioFormat;
+use crate::metadata::{fetch_metadata, sanitized_output_path, tag_file, TrackMetadata};
 use crate::setup::check_dependencies;
+use crate::spotify::{download_song_spotify, is_spotify_url};
 use clap::Parser;
+mod config;
+mod metadata;
 mod setup;
+mod spotify;
 use dialoguer::Select;
 use futures::future::join_all;
-use indicatif::{ProgressBar, ProgressStyle};
-use regex::Regex;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
-use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command as TokioCommand;
 use tokio::sync::Semaphore;
 use tokio::task;
 
@@ -84,6 +91,76 @@ async fn get_playlist_urls(
     Ok(urls)
 }
 
+const PROGRESS_TEMPLATE: &str =
+    "download:%(progress._percent_str)s %(progress._speed_str)s %(progress.filename)s";
+
+/// Parses one line of yt-dlp's `--newline` output and, if it's a
+/// `download:` progress line, updates `bar` with the reported percent and
+/// speed. A line mentioning `ExtractAudio` or `Post-process` (the ffmpeg
+/// handoff) flips the bar into a "Converting" state so the dashboard keeps
+/// moving during the non-download phase; any other line is ignored.
+fn apply_progress_line(bar: &ProgressBar, line: &str) {
+    if let Some(rest) = line.strip_prefix("download:") {
+        let mut parts = rest.split_whitespace();
+        if let (Some(percent), Some(speed)) = (parts.next(), parts.next()) {
+            if let Ok(pct) = percent.trim_end_matches('%').parse::<f64>() {
+                bar.set_position(pct.round() as u64);
+            }
+            bar.set_message(format!("Downloading @ {}", speed));
+        }
+    } else if line.contains("ExtractAudio") || line.contains("Post-process") {
+        bar.set_message("Converting".to_string());
+    }
+}
+
+/// If the track's genre has a configured output folder, returns the path it
+/// should be moved to; otherwise leaves it where `download_dir` put it.
+fn relocate_for_genre(
+    current_path: &std::path::Path,
+    track: &TrackMetadata,
+    genre_output_dirs: &Option<HashMap<String, String>>,
+) -> Result<Option<PathBuf>, std::io::Error> {
+    let Some(genre_output_dirs) = genre_output_dirs else {
+        return Ok(None);
+    };
+    let Some(genre) = track.output_genre() else {
+        return Ok(None);
+    };
+    let Some(folder) = genre_output_dirs.get(genre) else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(folder)?;
+    let file_name = current_path
+        .file_name()
+        .expect("downloaded file always has a name");
+    let ext = current_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    // Route through the same collision-safe path builder used for the
+    // initial placement, so a track that sanitizes to a name already
+    // present in this genre folder (from this run or a prior one) doesn't
+    // silently clobber it.
+    let candidate = PathBuf::from(folder).join(file_name);
+    let mut target = sanitized_output_path(&candidate, track, ext);
+    if target == candidate && candidate.exists() {
+        // `sanitized_output_path` exempts its `current` argument from the
+        // on-disk check, but this file isn't in `folder` yet, so a file
+        // already there is another track's. Asking again now that the name
+        // is reserved moves on to the next free suffix.
+        target = sanitized_output_path(&candidate, track, ext);
+    }
+    Ok(Some(target))
+}
+
+/// Whether `download_song` actually fetched a new file or yt-dlp skipped it
+/// because the download archive already had its ID recorded.
+enum DownloadOutcome {
+    Downloaded,
+    Skipped,
+}
+
 async fn download_song(
     yt_dlp_path: PathBuf,
     url: String,
@@ -91,7 +168,19 @@ async fn download_song(
     download_dir: String,
     download_thumbnail: bool,
     ffmpeg_path: PathBuf,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    bar: ProgressBar,
+    genre_output_dirs: Option<HashMap<String, String>>,
+    use_archive: bool,
+) -> Result<DownloadOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let track = {
+        let yt_dlp_path = yt_dlp_path.clone();
+        let url = url.clone();
+        task::spawn_blocking(move || fetch_metadata(&yt_dlp_path, &url)).await??
+    };
+    bar.set_message(format!("Queued: {}", track.file_stem()));
+
+    let archive_path = PathBuf::from(&download_dir).join(".ytpd-archive.txt");
+
     let mut args = vec![
         "--ffmpeg-location",
         ffmpeg_path.to_str().unwrap(),
@@ -108,69 +197,86 @@ async fn download_song(
 
     args.extend_from_slice(&["--postprocessor-args", "-ar 48000 -ac 2 -b:a 320k"]);
 
+    let archive_path_str = archive_path.to_str().expect("archive path is valid utf-8");
+    if use_archive {
+        args.extend_from_slice(&["--download-archive", archive_path_str]);
+    }
+
     args.extend_from_slice(&[
         "-P",
         &download_dir,
         "--no-check-certificates",
         "--ignore-errors",
+        "--newline",
+        "--progress-template",
+        PROGRESS_TEMPLATE,
         "--print",
         "after_move:%(filepath)s",
         "--output",
         "%(title)s.%(ext)s",
-        "--parse-metadata",
-        "%(uploader)s:%(artist)s",
-        "--replace-in-metadata",
-        "title",
-        "^.*? - ",
-        "",
-        "--replace-in-metadata",
-        "title",
-        "\\s*\\([^)]*\\)",
-        "",
-        "--add-metadata",
         &url,
     ]);
 
-    let output = Command::new(&yt_dlp_path)
+    let mut child = TokioCommand::new(&yt_dlp_path)
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .output()?;
+        .spawn()?;
 
-    if output.status.success() {
-        if let Ok(filepath) = String::from_utf8(output.stdout) {
-            let filepath = filepath.trim();
-            if !filepath.is_empty() {
-                let path = PathBuf::from(filepath);
-                if let Some(dir) = path.parent() {
-                    if let Some(filename) = path.file_name() {
-                        if let Some(filename_str) = filename.to_str() {
-                            let re_artist = Regex::new(r"^.*? - ").unwrap();
-                            let mut new_filename = re_artist.replace(filename_str, "").to_string();
-
-                            let re_prod = Regex::new(r"\s*\([^)]*\)").unwrap();
-                            new_filename = re_prod.replace_all(&new_filename, "").to_string();
-
-                            let re_spaces = Regex::new(r"\s+\.").unwrap();
-                            new_filename = re_spaces.replace_all(&new_filename, ".").to_string();
-
-                            let new_path = dir.join(&new_filename);
-
-                            if path != new_path {
-                                fs::rename(&path, &new_path)?;
-                                fs::rename(&new_path, &new_path)?;
-                            }
-                        }
-                    }
-                }
-            }
+    let stdout = child.stdout.take().expect("child stdout was piped");
+    let mut lines = BufReader::new(stdout).lines();
+
+    let mut filepath = None;
+    while let Some(line) = lines.next_line().await? {
+        apply_progress_line(&bar, &line);
+        if let Some(path) = line.strip_prefix("after_move:") {
+            filepath = Some(path.trim().to_string());
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bar.abandon_with_message("Failed");
+        return Err(format!("Failed to download {}", url).into());
+    }
+
+    let Some(filepath) = filepath.filter(|path| !path.is_empty()) else {
+        // No `after_move:` line means yt-dlp never actually moved a file
+        // into place: `--download-archive` skipped a video it already had
+        // recorded, and there's nothing left to tag or relocate.
+        bar.finish_with_message("Already downloaded");
+        return Ok(DownloadOutcome::Skipped);
+    };
+
+    bar.set_position(100);
+    bar.set_message("Tagging".to_string());
+
+    let path = PathBuf::from(filepath);
+    if path.parent().is_some() {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or(audio_format.as_str());
+        let new_path = sanitized_output_path(&path, &track, ext);
+
+        if path != new_path {
+            fs::rename(&path, &new_path)?;
+        }
+
+        {
+            let ffmpeg_path = ffmpeg_path.clone();
+            let new_path = new_path.clone();
+            let track = track.clone();
+            task::spawn_blocking(move || tag_file(&ffmpeg_path, &new_path, &track)).await??;
+        }
+
+        if let Some(final_path) = relocate_for_genre(&new_path, &track, &genre_output_dirs)? {
+            fs::rename(&new_path, &final_path)?;
         }
-        Ok(())
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        println!("Failed to download {}: {}", url, error);
-        Err(error.into())
     }
+
+    bar.finish_with_message("Done");
+    Ok(DownloadOutcome::Downloaded)
 }
 
 #[derive(Parser)]
@@ -178,32 +284,24 @@ async fn download_song(
 #[command(about = "Youtube Music Downloader")]
 struct Cli {
     url: Option<String>,
-}
 
-#[derive(Clone, Copy)]
-enum AudioFormat {
-    Mp3,
-    Wav,
-    M4a,
-    Aac,
-    Flac,
-}
+    /// Re-run the interactive setup and prompts even if ytpd/config.json
+    /// already has a complete, saved configuration.
+    #[arg(long)]
+    reconfigure: bool,
 
-impl AudioFormat {
-    fn as_str(&self) -> &'static str {
-        match self {
-            AudioFormat::Mp3 => "mp3",
-            AudioFormat::Wav => "wav",
-            AudioFormat::M4a => "m4a",
-            AudioFormat::Aac => "aac",
-            AudioFormat::Flac => "flac",
-        }
-    }
+    /// Force re-downloading tracks even if they're already recorded in the
+    /// download directory's archive file.
+    #[arg(long)]
+    no_archive: bool,
 }
 
 #[tokio::main]
 async fn main() {
-    let setup_config = match check_dependencies().await {
+    let cli = Cli::parse();
+    let use_archive = !cli.no_archive;
+
+    let setup_config = match check_dependencies(cli.reconfigure).await {
         Ok(config) => config,
         Err(e) => {
             eprintln!("Setup failed: {}", e);
@@ -211,11 +309,7 @@ async fn main() {
         }
     };
 
-    let yt_dlp_path = setup_config.yt_dlp_path;
-
-    let download_dir = get_download_directory().expect("Failed to setup download directory");
-
-    let cli = Cli::parse();
+    let yt_dlp_path = setup_config.yt_dlp_path.clone();
 
     let url = if let Some(url) = cli.url {
         url
@@ -230,48 +324,122 @@ async fn main() {
         input.trim().to_string()
     };
 
+    let is_spotify = is_spotify_url(&url);
     let is_playlist = url.contains("playlist?list=");
-    let options = vec!["Single Song", "Playlist"];
-    let selection = Select::new()
-        .with_prompt("Choose download type")
-        .items(&options)
-        .default(0)
-        .interact()
-        .unwrap();
 
-    if selection == 0 && is_playlist {
-        println!("Error: This is a playlist/album URL. Please provide a single song URL for single song download.");
-        return;
-    }
+    let (download_dir, audio_format, download_thumbnail, genre_output_dirs, concurrency) =
+        match &setup_config.saved_config {
+            Some(saved) => (
+                saved.download_dir.clone(),
+                saved.audio_format,
+                saved.embed_thumbnail,
+                saved.genre_output_dirs.clone(),
+                saved.concurrency,
+            ),
+            None => {
+                let download_dir =
+                    get_download_directory().expect("Failed to setup download directory");
+
+                let format_options = vec!["MP3", "WAV", "M4A", "AAC", "FLAC"];
+                let format_selection = Select::new()
+                    .with_prompt("Choose audio format")
+                    .items(&format_options)
+                    .default(0)
+                    .interact()
+                    .unwrap();
+
+                let audio_format = match format_selection {
+                    0 => AudioFormat::Mp3,
+                    1 => AudioFormat::Wav,
+                    2 => AudioFormat::M4a,
+                    3 => AudioFormat::Aac,
+                    4 => AudioFormat::Flac,
+                    _ => AudioFormat::Mp3,
+                };
+
+                let download_thumbnail = should_download_thumbail();
+
+                let new_config = config::Config {
+                    yt_dlp_path: setup_config.yt_dlp_path.clone(),
+                    ffmpeg_path: setup_config.ffmpeg_path.clone(),
+                    audio_format,
+                    download_dir: download_dir.clone(),
+                    embed_thumbnail: download_thumbnail,
+                    concurrency: 44,
+                    genre_output_dirs: None,
+                };
+                if let Err(e) = config::save(&new_config) {
+                    println!("Warning: failed to save config: {}", e);
+                }
 
-    let format_options = vec!["MP3", "WAV", "M4A", "AAC", "FLAC"];
-    let format_selection = Select::new()
-        .with_prompt("Choose audio format")
-        .items(&format_options)
-        .default(0)
-        .interact()
-        .unwrap();
+                (download_dir, audio_format, download_thumbnail, None, 44)
+            }
+        };
+
+    let selection = if is_spotify {
+        // spotdl resolves tracks, albums, and playlists from the same
+        // command, so there's no separate "playlist mode" to choose.
+        0
+    } else if setup_config.saved_config.is_some() {
+        // A saved config means we're in one-shot mode: infer the download
+        // type from the URL instead of prompting.
+        usize::from(is_playlist)
+    } else {
+        let options = vec!["Single Song", "Playlist"];
+        let selection = Select::new()
+            .with_prompt("Choose download type")
+            .items(&options)
+            .default(0)
+            .interact()
+            .unwrap();
+
+        if selection == 0 && is_playlist {
+            println!("Error: This is a playlist/album URL. Please provide a single song URL for single song download.");
+            return;
+        }
 
-    let audio_format = match format_selection {
-        0 => AudioFormat::Mp3,
-        1 => AudioFormat::Wav,
-        2 => AudioFormat::M4a,
-        3 => AudioFormat::Aac,
-        4 => AudioFormat::Flac,
-        _ => AudioFormat::Mp3,
+        selection
     };
 
-    let download_thumbnail = should_download_thumbail();
+    if is_spotify {
+        let Some(python_path) = setup_config.python_path else {
+            println!("Spotify downloads require Python and spotdl; none was found during setup.");
+            return;
+        };
+        if !setup_config.spotdl_available {
+            println!("Spotify downloads require spotdl, which could not be installed during setup.");
+            return;
+        }
+
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner} Downloading via spotdl... {wide_msg}")
+                .unwrap(),
+        );
+        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+        let result =
+            download_song_spotify(python_path, url, audio_format.as_str(), download_dir).await;
+        spinner.finish_and_clear();
+        match result {
+            Ok(_) => println!("Download completed!"),
+            Err(e) => println!("Download failed: {}", e),
+        }
+        return;
+    }
 
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner} Downloading... {wide_msg}")
-            .unwrap(),
-    );
-    spinner.enable_steady_tick(Duration::from_millis(100));
+    let bar_style = ProgressStyle::default_bar()
+        .template("{prefix:>3} [{bar:30}] {pos:>3}% {msg}")
+        .unwrap()
+        .progress_chars("=> ");
 
     if selection == 0 {
+        let multi_progress = MultiProgress::new();
+        let bar = multi_progress.add(ProgressBar::new(100));
+        bar.set_style(bar_style);
+        bar.set_prefix("1");
+
         let result = download_song(
             yt_dlp_path,
             url,
@@ -279,11 +447,16 @@ async fn main() {
             download_dir,
             download_thumbnail,
             setup_config.ffmpeg_path.unwrap(),
+            bar,
+            genre_output_dirs,
+            use_archive,
         )
         .await;
-        spinner.finish_and_clear();
         match result {
-            Ok(_) => println!("Download completed!"),
+            Ok(DownloadOutcome::Downloaded) => println!("Download completed!"),
+            Ok(DownloadOutcome::Skipped) => {
+                println!("Already downloaded, skipped (use --no-archive to force)")
+            }
             Err(e) => println!("Download failed: {}", e),
         }
     } else {
@@ -293,16 +466,22 @@ async fn main() {
                 println!("Starting download of {} videos...", playlist_urls.len());
                 let mut handles = vec![];
 
-                let semaphore = Arc::new(Semaphore::new(44));
+                let semaphore = Arc::new(Semaphore::new(concurrency));
 
                 let ffmpeg_path = setup_config.ffmpeg_path.unwrap().clone();
+                let multi_progress = MultiProgress::new();
 
-                for url in playlist_urls.iter() {
+                for (index, url) in playlist_urls.iter().enumerate() {
                     let yt_dlp_path = yt_dlp_path.clone();
                     let url = url.to_string();
                     let download_dir = download_dir.clone();
                     let sem = semaphore.clone();
                     let ffmpeg_path = ffmpeg_path.clone();
+                    let genre_output_dirs = genre_output_dirs.clone();
+
+                    let bar = multi_progress.add(ProgressBar::new(100));
+                    bar.set_style(bar_style.clone());
+                    bar.set_prefix((index + 1).to_string());
 
                     let handle = task::spawn(async move {
                         let _permit = sem.acquire().await.unwrap();
@@ -313,6 +492,9 @@ async fn main() {
                             download_dir,
                             download_thumbnail,
                             ffmpeg_path,
+                            bar,
+                            genre_output_dirs,
+                            use_archive,
                         )
                         .await
                     });
@@ -320,24 +502,24 @@ async fn main() {
                 }
 
                 let results = join_all(handles).await;
-                spinner.finish_and_clear();
 
-                let mut success_count = 0;
+                let mut downloaded_count = 0;
+                let mut skipped_count = 0;
                 let mut failure_count = 0;
                 for result in results {
                     match result {
-                        Ok(Ok(_)) => success_count += 1,
+                        Ok(Ok(DownloadOutcome::Downloaded)) => downloaded_count += 1,
+                        Ok(Ok(DownloadOutcome::Skipped)) => skipped_count += 1,
                         _ => failure_count += 1,
                     }
                 }
 
                 println!(
-                    "Playlist download completed! Successful: {}, Failed: {}",
-                    success_count, failure_count
+                    "Playlist sync completed! Downloaded: {}, Already up to date: {}, Failed: {}",
+                    downloaded_count, skipped_count, failure_count
                 );
             }
             Err(e) => {
-                spinner.finish_and_clear();
                 println!("Failed to get playlist URLs: {}", e);
             }
         }
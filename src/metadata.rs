@@ -0,0 +1,243 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// A single track's metadata as reported by yt-dlp's JSON dump.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrackMetadata {
+    pub title: String,
+    pub uploader: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<u32>,
+    pub release_year: Option<u32>,
+    pub thumbnail: Option<String>,
+    /// Populated by extractors like Bandcamp/SoundCloud; yt-dlp's YouTube
+    /// extractor never sets this, so for YouTube sources fall back to
+    /// `categories` via [`TrackMetadata::output_genre`].
+    pub genre: Option<String>,
+    /// YouTube's broad content category (e.g. "Music", "Gaming"), present on
+    /// essentially every video; the closest thing to a genre yt-dlp's
+    /// YouTube extractor actually reports.
+    pub categories: Option<Vec<String>>,
+}
+
+impl TrackMetadata {
+    /// Best-effort artist, falling back to the channel/uploader name when
+    /// yt-dlp couldn't parse one out of the video's own metadata.
+    pub fn display_artist(&self) -> String {
+        self.artist
+            .clone()
+            .or_else(|| self.uploader.clone())
+            .unwrap_or_else(|| "Unknown Artist".to_string())
+    }
+
+    /// Genre to key the per-genre output-folder map off of: the real
+    /// `genre` tag where an extractor provides one, falling back to
+    /// YouTube's top-level category since plain YouTube videos never carry
+    /// an actual genre tag.
+    pub fn output_genre(&self) -> Option<&str> {
+        self.genre
+            .as_deref()
+            .or_else(|| self.categories.as_ref()?.first().map(String::as_str))
+    }
+
+    /// "Artist - Title" stem used for the on-disk filename, without extension.
+    pub fn file_stem(&self) -> String {
+        format!("{} - {}", self.display_artist(), self.title)
+    }
+
+    /// `-metadata key=value` pairs for tagging the output file with ffmpeg.
+    pub fn ffmpeg_metadata_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-metadata".to_string(),
+            format!("title={}", self.title),
+            "-metadata".to_string(),
+            format!("artist={}", self.display_artist()),
+        ];
+
+        if let Some(album) = &self.album {
+            args.push("-metadata".to_string());
+            args.push(format!("album={}", album));
+        }
+        if let Some(track) = self.track {
+            args.push("-metadata".to_string());
+            args.push(format!("track={}", track));
+        }
+        if let Some(year) = self.release_year {
+            args.push("-metadata".to_string());
+            args.push(format!("date={}", year));
+        }
+
+        args
+    }
+}
+
+/// yt-dlp's `--dump-single-json` returns either a single video object or a
+/// playlist object with an `entries` array; this mirrors that shape so the
+/// same call site handles both without guessing ahead of time.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum YoutubeDlOutput {
+    Playlist(PlaylistMetadata),
+    SingleVideo(Box<TrackMetadata>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistMetadata {
+    pub entries: Vec<TrackMetadata>,
+}
+
+/// Runs yt-dlp once against `url` to fetch structured metadata without
+/// downloading anything, so callers can derive filenames and tags up front
+/// instead of regexing the post-download filename.
+pub fn fetch_metadata(
+    yt_dlp_path: &Path,
+    url: &str,
+) -> Result<TrackMetadata, Box<dyn std::error::Error + Send + Sync>> {
+    let output = Command::new(yt_dlp_path)
+        .args([
+            "--dump-single-json",
+            "--no-warnings",
+            "--no-check-certificates",
+            "--ignore-errors",
+            url,
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to fetch metadata for {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    let parsed: YoutubeDlOutput = serde_json::from_slice(&output.stdout)?;
+    match parsed {
+        YoutubeDlOutput::SingleVideo(meta) => Ok(*meta),
+        YoutubeDlOutput::Playlist(playlist) => playlist
+            .entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No entries found for {}", url).into()),
+    }
+}
+
+/// Tags `path` in place with `metadata` using ffmpeg, copying the audio
+/// stream untouched so only the container tags change.
+pub fn tag_file(
+    ffmpeg_path: &Path,
+    path: &Path,
+    metadata: &TrackMetadata,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tagged_path = path.with_extension(format!(
+        "tagged.{}",
+        path.extension().and_then(|e| e.to_str()).unwrap_or("")
+    ));
+
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        path.to_string_lossy().to_string(),
+    ];
+    args.extend(metadata.ffmpeg_metadata_args());
+    args.extend([
+        "-codec".to_string(),
+        "copy".to_string(),
+        tagged_path.to_string_lossy().to_string(),
+    ]);
+
+    let output = Command::new(ffmpeg_path).args(&args).output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to tag {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    std::fs::rename(&tagged_path, path)?;
+    Ok(())
+}
+
+/// Characters that are illegal (or awkward) in filenames on at least one of
+/// Windows/macOS/Linux: path separators and NTFS-reserved punctuation.
+/// Control characters are caught separately in `sanitize_filename_component`.
+const RESERVED_FILENAME_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Maximum length of the sanitized stem in bytes, well under the 255-byte filename
+/// limits most filesystems enforce once the extension is added back.
+const MAX_STEM_LEN: usize = 150;
+
+/// Makes `name` safe to use as a filename across Windows/macOS/Linux:
+/// reserved characters and control characters become `_`, and trailing
+/// dots/spaces (which Windows silently strips, causing the file to reappear
+/// under a different name than the one we just wrote) are trimmed.
+fn sanitize_filename_component(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if RESERVED_FILENAME_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    if sanitized.len() > MAX_STEM_LEN {
+        let mut boundary = MAX_STEM_LEN;
+        while !sanitized.is_char_boundary(boundary) {
+            boundary -= 1;
+        }
+        sanitized.truncate(boundary);
+    }
+
+    let trimmed = sanitized.trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        "untitled".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Paths already handed out by `sanitized_output_path` during this process's
+/// lifetime. Playlist tracks are tagged/renamed concurrently (up to
+/// `concurrency` at once), so a plain `path.exists()` check-then-rename race
+/// isn't enough on its own: two tasks can both see the same stem as free
+/// before either has created the file. Claiming the path under this lock
+/// closes that window.
+fn reserved_paths() -> &'static Mutex<HashSet<PathBuf>> {
+    static RESERVED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    RESERVED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Builds the output path for `metadata` alongside `current` (the file
+/// yt-dlp just produced), sanitizing the artist/title stem and, if that path
+/// is already taken (on disk or reserved by a concurrent call in this run),
+/// appending a numeric suffix until one is free. A file on disk at `current`
+/// itself never counts as a collision, since renaming a file to its own current name is a
+/// no-op rather than a clash with some other track. The returned path is
+/// reserved for the caller and won't be handed out again.
+pub fn sanitized_output_path(current: &Path, metadata: &TrackMetadata, ext: &str) -> PathBuf {
+    let dir = current.parent().unwrap_or_else(|| Path::new("."));
+    let stem = sanitize_filename_component(&metadata.file_stem());
+    let mut reserved = reserved_paths().lock().unwrap();
+
+    let candidates = std::iter::once(dir.join(format!("{}.{}", stem, ext)))
+        .chain((2..).map(|n| dir.join(format!("{} ({}).{}", stem, n, ext))));
+
+    let path = candidates
+        .find(|candidate| {
+            (candidate == current || !candidate.exists()) && !reserved.contains(candidate)
+        })
+        .expect("an integer suffix eventually finds a free path");
+
+    reserved.insert(path.clone());
+    path
+}
@@ -1,3 +1,4 @@
+use crate::config::{self, Config};
 use dialoguer::Select;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
@@ -9,27 +10,91 @@ use std::time::Duration;
 pub struct SetupConfig {
     pub yt_dlp_path: PathBuf,
     pub ffmpeg_path: Option<PathBuf>,
+    /// Whether `python -m spotdl` is ready to use, so `main` can give a
+    /// clear upfront error for Spotify URLs instead of failing deep inside
+    /// the spotdl subprocess invocation.
+    pub spotdl_available: bool,
+    pub python_path: Option<PathBuf>,
+    /// Present when `ytpd/config.json` exists and was honored this run, so
+    /// `main` can skip straight past the interactive prompts it covers.
+    pub saved_config: Option<Config>,
 }
 
-pub async fn check_dependencies() -> Result<SetupConfig, Box<dyn std::error::Error>> {
-    let ffmpeg_path = match check_ffmpeg() {
-        Ok(path) => Some(path),
+pub async fn check_dependencies(
+    reconfigure: bool,
+) -> Result<SetupConfig, Box<dyn std::error::Error>> {
+    let saved_config = if reconfigure { None } else { config::load() };
+
+    let saved_paths_valid = saved_config.as_ref().is_some_and(|saved| {
+        saved.yt_dlp_path.exists()
+            && saved
+                .ffmpeg_path
+                .as_ref()
+                .is_some_and(|path| path.exists())
+    });
+
+    let (yt_dlp_path, ffmpeg_path) = match &saved_config {
+        Some(saved) if saved_paths_valid => {
+            (saved.yt_dlp_path.clone(), saved.ffmpeg_path.clone())
+        }
+        _ => (resolve_yt_dlp().await?, resolve_ffmpeg().await?),
+    };
+
+    let python_path = find_python();
+
+    let spotdl_available = match &python_path {
+        Some(python_path) => match check_spotdl(python_path) {
+            Ok(_) => true,
+            Err(_) => {
+                println!("⨯ spotdl not found");
+                match install_spotdl(python_path).await {
+                    Ok(_) => {
+                        println!("✓ spotdl installed successfully");
+                        true
+                    }
+                    Err(e) => {
+                        println!("⨯ Failed to install spotdl: {}", e);
+                        false
+                    }
+                }
+            }
+        },
+        None => {
+            println!("⨯ Python not found, Spotify links will not be available");
+            false
+        }
+    };
+
+    Ok(SetupConfig {
+        yt_dlp_path,
+        ffmpeg_path,
+        spotdl_available,
+        python_path,
+        saved_config,
+    })
+}
+
+async fn resolve_ffmpeg() -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    match check_ffmpeg() {
+        Ok(path) => Ok(Some(path)),
         Err(_) => {
             println!("⨯ FFmpeg not found");
             show_ffmpeg_install_instructions();
             match install_ffmpeg().await {
                 Ok(path) => {
                     println!("✓ FFmpeg installed successfully");
-                    Some(path)
+                    Ok(Some(path))
                 }
                 Err(e) => {
                     println!("⨯ Failed to install FFmpeg: {}", e);
-                    return Err(e);
+                    Err(e)
                 }
             }
         }
-    };
+    }
+}
 
+async fn resolve_yt_dlp() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let bin_dir = PathBuf::from("ytpd");
     let yt_dlp_path = if cfg!(windows) {
         bin_dir.join("yt-dlp.exe")
@@ -37,26 +102,77 @@ pub async fn check_dependencies() -> Result<SetupConfig, Box<dyn std::error::Err
         bin_dir.join("yt-dlp")
     };
 
-    let yt_dlp_path = if yt_dlp_path.exists() {
-        yt_dlp_path
+    if yt_dlp_path.exists() {
+        return Ok(yt_dlp_path);
+    }
+
+    println!("⨯ yt-dlp not found");
+    match ensure_yt_dlp().await {
+        Ok(path) => {
+            println!("✓ yt-dlp installed successfully");
+            Ok(path)
+        }
+        Err(e) => {
+            println!("⨯ Failed to install yt-dlp: {}", e);
+            Err(e)
+        }
+    }
+}
+
+fn find_python() -> Option<PathBuf> {
+    let candidates = if cfg!(windows) {
+        vec!["python", "python3"]
     } else {
-        println!("⨯ yt-dlp not found");
-        match ensure_yt_dlp().await {
-            Ok(path) => {
-                println!("✓ yt-dlp installed successfully");
-                path
-            }
-            Err(e) => {
-                println!("⨯ Failed to install yt-dlp: {}", e);
-                return Err(e);
+        vec!["python3", "python"]
+    };
+
+    for candidate in candidates {
+        if let Ok(output) = Command::new(candidate).arg("--version").output() {
+            if output.status.success() {
+                return Some(PathBuf::from(candidate));
             }
         }
-    };
+    }
 
-    Ok(SetupConfig {
-        yt_dlp_path,
-        ffmpeg_path,
-    })
+    None
+}
+
+fn check_spotdl(python_path: &PathBuf) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let output = Command::new(python_path)
+        .args(["-m", "spotdl", "--version"])
+        .output()?;
+
+    if !output.status.success() {
+        return Err("spotdl not found".into());
+    }
+
+    Ok(python_path.clone())
+}
+
+async fn install_spotdl(python_path: &PathBuf) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner} Installing spotdl... {wide_msg}")
+            .unwrap(),
+    );
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let output = Command::new(python_path)
+        .args(["-m", "pip", "install", "--upgrade", "spotdl"])
+        .output()?;
+
+    spinner.finish_and_clear();
+
+    if output.status.success() {
+        check_spotdl(python_path)
+    } else {
+        Err(format!(
+            "Failed to install spotdl: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into())
+    }
 }
 
 fn check_ffmpeg() -> Result<PathBuf, Box<dyn std::error::Error>> {
@@ -137,81 +253,124 @@ fn show_ffmpeg_install_instructions() {
     }
 }
 
-async fn install_ffmpeg() -> Result<PathBuf, Box<dyn std::error::Error>> {
+enum FfmpegArchive {
+    Zip,
+    TarXz,
+}
+
+/// Static-build release URL for the current OS, mirroring the URLs yt-dlp
+/// itself is downloaded from in `ensure_yt_dlp`.
+fn ffmpeg_release_url() -> Result<(&'static str, FfmpegArchive), Box<dyn std::error::Error>> {
     if cfg!(target_os = "windows") {
-        install_ffmpeg_windows().await
+        Ok((
+            "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl.zip",
+            FfmpegArchive::Zip,
+        ))
     } else if cfg!(target_os = "macos") {
-        install_ffmpeg_macos().await
+        Ok((
+            "https://evermeet.cx/ffmpeg/getrelease/zip",
+            FfmpegArchive::Zip,
+        ))
+    } else if cfg!(target_os = "linux") {
+        Ok((
+            "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linux64-gpl.tar.xz",
+            FfmpegArchive::TarXz,
+        ))
     } else {
-        install_ffmpeg_linux().await
+        Err("No static FFmpeg build is available for this platform".into())
     }
 }
 
-async fn install_ffmpeg_windows() -> Result<PathBuf, Box<dyn std::error::Error>> {
+fn extract_zip(bytes: &[u8], dest: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    archive.extract(dest)?;
+    Ok(())
+}
+
+fn extract_tar_xz(bytes: &[u8], dest: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let decompressed = xz2::read::XzDecoder::new(bytes);
+    tar::Archive::new(decompressed).unpack(dest)?;
+    Ok(())
+}
+
+/// Static FFmpeg builds bury the binary a few directories deep under a
+/// version-specific folder name, so we just walk the extracted tree for it
+/// rather than hardcoding that path.
+fn find_file(dir: &std::path::Path, name: &str) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name().and_then(|f| f.to_str()) == Some(name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Downloads and unpacks a static FFmpeg build into `bin_dir`, next to the
+/// vendored yt-dlp binary, so neither a package manager nor admin rights are
+/// required. Mirrors `ensure_yt_dlp`'s approach of vendoring a static binary.
+async fn install_ffmpeg() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let bin_dir = PathBuf::from("ytpd");
     fs::create_dir_all(&bin_dir)?;
 
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
         ProgressStyle::default_spinner()
-            .template("{spinner} Installing FFmpeg... {wide_msg}")
+            .template("{spinner} Downloading FFmpeg... {wide_msg}")
             .unwrap(),
     );
     spinner.enable_steady_tick(Duration::from_millis(100));
 
-    println!("Windows FFmpeg automatic installation not implemented yet.");
-    println!("Please install FFmpeg manually from https://ffmpeg.org/download.html");
-    Err("Windows FFmpeg installation not implemented yet".into())
+    let result = download_and_unpack_ffmpeg(&bin_dir).await;
+
+    spinner.finish_and_clear();
+    result
 }
 
-async fn install_ffmpeg_macos() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner} Installing FFmpeg via Homebrew... {wide_msg}")
-            .unwrap(),
-    );
-    spinner.enable_steady_tick(Duration::from_millis(100));
+async fn download_and_unpack_ffmpeg(
+    bin_dir: &PathBuf,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (url, archive) = ffmpeg_release_url()?;
 
-    let output = Command::new("brew").args(["install", "ffmpeg"]).output()?;
+    let response = reqwest::get(url).await?;
+    let bytes = response.bytes().await?;
 
-    spinner.finish_and_clear();
+    let extract_dir = bin_dir.join("ffmpeg-extract");
+    fs::create_dir_all(&extract_dir)?;
 
-    if output.status.success() {
-        check_ffmpeg()
-    } else {
-        Err("Failed to install FFmpeg via Homebrew".into())
+    match archive {
+        FfmpegArchive::Zip => extract_zip(&bytes, &extract_dir)?,
+        FfmpegArchive::TarXz => extract_tar_xz(&bytes, &extract_dir)?,
     }
-}
 
-async fn install_ffmpeg_linux() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner} Installing FFmpeg... {wide_msg}")
-            .unwrap(),
-    );
-    spinner.enable_steady_tick(Duration::from_millis(100));
-
-    let (cmd, args) = if Command::new("apt").output().is_ok() {
-        ("sudo", vec!["apt", "install", "-y", "ffmpeg"])
-    } else if Command::new("dnf").output().is_ok() {
-        ("sudo", vec!["dnf", "install", "-y", "ffmpeg"])
-    } else if Command::new("pacman").output().is_ok() {
-        ("sudo", vec!["pacman", "-S", "--noconfirm", "ffmpeg"])
+    let binary_name = if cfg!(windows) {
+        "ffmpeg.exe"
     } else {
-        return Err("No supported package manager found".into());
+        "ffmpeg"
     };
+    let extracted_binary = find_file(&extract_dir, binary_name)
+        .ok_or("Downloaded FFmpeg archive did not contain an ffmpeg binary")?;
 
-    let output = Command::new(cmd).args(&args).output()?;
+    let ffmpeg_path = bin_dir.join(binary_name);
+    fs::rename(&extracted_binary, &ffmpeg_path)?;
+    let _ = fs::remove_dir_all(&extract_dir);
 
-    spinner.finish_and_clear();
+    if !cfg!(windows) {
+        let mut perms = fs::metadata(&ffmpeg_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&ffmpeg_path, perms)?;
+    }
 
-    if output.status.success() {
-        check_ffmpeg()
-    } else {
-        Err("Failed to install FFmpeg".into())
+    let output = Command::new(&ffmpeg_path).arg("-version").output()?;
+    if !output.status.success() {
+        return Err("Downloaded FFmpeg binary failed verification".into());
     }
+
+    Ok(ffmpeg_path)
 }
 
 async fn ensure_yt_dlp() -> Result<PathBuf, Box<dyn std::error::Error>> {
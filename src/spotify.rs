@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// True for Spotify track/album/playlist links, which yt-dlp can't resolve
+/// and need to be routed through spotdl instead.
+pub fn is_spotify_url(url: &str) -> bool {
+    url.contains("open.spotify.com")
+}
+
+/// Downloads a Spotify URL (track, album, or playlist) via spotdl, which is
+/// itself a Python module rather than a standalone binary, so it's invoked
+/// as `python -m spotdl`.
+pub async fn download_song_spotify(
+    python_path: PathBuf,
+    url: String,
+    audio_format: &str,
+    download_dir: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let output_template = format!("{}/{{artists}} - {{title}}.{{output-ext}}", download_dir);
+
+    let output = Command::new(&python_path)
+        .args([
+            "-m",
+            "spotdl",
+            "download",
+            &url,
+            "--format",
+            audio_format,
+            "--output",
+            &output_template,
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        println!("Failed to download {}: {}", url, error);
+        return Err(error.into());
+    }
+
+    Ok(())
+}